@@ -0,0 +1,101 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Program-lifetime cache of repository and worktree roots seen so far,
+/// keyed by resolved path -- mirrors exa's git cache. Search roots or nested
+/// directories that land on an already-discovered repo are resolved via a
+/// lookup here *before* paying for fresh detection (reading `config`,
+/// `worktrees/`, etc.), not just de-duplicated after the fact.
+pub struct GitCache {
+    known: Mutex<HashSet<PathBuf>>,
+}
+
+impl GitCache {
+    pub fn new() -> Self {
+        GitCache {
+            known: Mutex::new(HashSet::new()),
+        }
+    }
+
+    fn resolve(path: &Path) -> PathBuf {
+        fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+    }
+
+    /// True if `dir` was already recorded as a repo/worktree root, letting
+    /// callers skip running detection on it again.
+    pub fn is_known(&self, dir: &Path) -> bool {
+        self.known.lock().unwrap().contains(&Self::resolve(dir))
+    }
+
+    /// Records `dir` as a discovered repo/worktree root. Returns `true` the
+    /// first time a given resolved dir is recorded, `false` on later calls,
+    /// so callers can skip emitting a duplicate row for it.
+    pub fn record(&self, dir: &Path) -> bool {
+        self.known.lock().unwrap().insert(Self::resolve(dir))
+    }
+}
+
+impl Default for GitCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh, empty temp dir scoped to one test by name + pid, so parallel
+    /// test runs never collide.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "directory_finder-cache-test-{name}-{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn unknown_dir_is_not_known() {
+        let dir = temp_dir("unknown");
+        let cache = GitCache::new();
+        assert!(!cache.is_known(&dir));
+    }
+
+    #[test]
+    fn record_makes_dir_known() {
+        let dir = temp_dir("record");
+        let cache = GitCache::new();
+        assert!(cache.record(&dir));
+        assert!(cache.is_known(&dir));
+    }
+
+    #[test]
+    fn record_returns_false_on_second_call() {
+        let dir = temp_dir("record-twice");
+        let cache = GitCache::new();
+        assert!(cache.record(&dir));
+        assert!(!cache.record(&dir));
+    }
+
+    /// The scenario chunk0-5 exists for: the same repo reached via two
+    /// different search roots (e.g. `df a b` where `a/repo` and `b` ->
+    /// `a/repo` via a symlink) must resolve to the same cache entry and be
+    /// reported only once.
+    #[test]
+    fn same_repo_via_two_roots_is_recorded_once() {
+        let repo = temp_dir("shared-repo");
+        let alias = temp_dir("shared-repo-alias-parent").join("alias");
+        std::os::unix::fs::symlink(&repo, &alias).unwrap();
+
+        let cache = GitCache::new();
+        assert!(cache.record(&repo));
+        // Reached again via a different path that resolves to the same repo.
+        assert!(cache.is_known(&alias));
+        assert!(!cache.record(&alias));
+    }
+}
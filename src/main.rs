@@ -1,8 +1,20 @@
-use clap::Parser;
+mod cache;
+mod vcs;
+
+use cache::GitCache;
+use clap::{Parser, ValueEnum};
+use ignore::{WalkBuilder, WalkState};
+use serde::Serialize;
 use std::env;
 use std::path::{Path, PathBuf};
-use std::process::Command;
-use walkdir::WalkDir;
+use std::sync::Mutex;
+use vcs::{detectors, DirType, VersionControl};
+
+#[derive(Clone, Debug, ValueEnum)]
+enum OutputFormat {
+    Plain,
+    Json,
+}
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -14,6 +26,14 @@ struct Args {
     /// Maximum depth to search
     #[arg(short, long, default_value_t = 1)]
     depth: usize,
+
+    /// Search inside directories ignored by .gitignore/.git/info/exclude too
+    #[arg(long)]
+    no_ignore: bool,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t = OutputFormat::Plain)]
+    format: OutputFormat,
 }
 
 fn expand_path(path: &str, home: &Path) -> PathBuf {
@@ -26,187 +46,211 @@ fn expand_path(path: &str, home: &Path) -> PathBuf {
     }
 }
 
-fn is_git_repository(dir: &Path) -> bool {
-    if dir.join(".git").is_dir() {
-        // Este comando falla si es un dir normal. si es bare devuelve false pero
-        // no error aunque no entra en este
-        // .map(|output| output.status.success())
-        // .unwrap_or(false)
-        let output = Command::new("git")
-            .arg("-C")
-            .arg(dir)
-            .arg("rev-parse")
-            .arg("--is-inside-work-tree")
-            .output();
-        // let stdout_utf8 = std::str::from_utf8(&output.stdout);
-        let stdout_str = match output {
-            Ok(output) => String::from_utf8(output.stdout)
-                .unwrap_or_else(|_| "".to_string())
-                .trim()
-                .to_string(),
-            Err(_) => String::new(),
-        };
-        // println!("Output: {}", stdout_str);
-        // println!("{:?}", is_git_dir);
-        stdout_str.eq("true")
-    } else {
-        false
-    }
-}
-
-fn is_bare_repository(dir: &Path) -> bool {
-    if dir.join("HEAD").is_file() {
-        let output = Command::new("git")
-            .arg("-C")
-            .arg(dir)
-            .arg("rev-parse")
-            .arg("--is-inside-git-dir")
-            .output();
-        // let stdout_utf8 = std::str::from_utf8(&output.stdout);
-        let stdout_str = match output {
-            Ok(output) => String::from_utf8(output.stdout)
-                .unwrap_or_else(|_| "".to_string())
-                .trim()
-                .to_string(),
-            Err(_) => String::new(),
-        };
-        // println!("Output: {}", stdout_str);
-        // println!("{:?}", is_git_dir);
-
-        if stdout_str.eq("true") {
-            // Check if the directory name is not ".git"
-            return dir.file_name().map_or(false, |name| name != ".git");
-        } else {
-            return false;
-        };
-    }
-    false
-}
-
 #[derive(Clone)]
 struct ProjectDir {
     dir_type: DirType,
     path: PathBuf,
+    /// Recursion depth (from the original search root) at which this entry
+    /// was found. Worktrees inherit the depth of the repo that reported them.
+    depth: usize,
 }
 
-#[derive(Clone, PartialEq)]
-enum DirType {
-    BareGit,
-    WorkTree,
-    Git,
-    Dir,
+/// The subset of `ProjectDir` that gets serialized for `--format json`:
+/// `DirType` itself doesn't derive `Serialize`, so this mirrors it with the
+/// string tags from `DirType::tag`.
+#[derive(Serialize)]
+struct JsonEntry {
+    path: PathBuf,
+    dir_type: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    common_dir: Option<PathBuf>,
+    depth: usize,
 }
 
-fn list_worktrees(bare_repo_path: &Path) -> Option<Vec<PathBuf>> {
-    let output = Command::new("git")
-        .arg("-C")
-        .arg(bare_repo_path)
-        .arg("worktree")
-        .arg("list")
-        .output()
-        .ok()?;
-
-    if !output.status.success() {
-        return None;
+impl From<&ProjectDir> for JsonEntry {
+    fn from(entry: &ProjectDir) -> Self {
+        let common_dir = match &entry.dir_type {
+            DirType::WorkTree { common_dir } => Some(common_dir.clone()),
+            _ => None,
+        };
+        JsonEntry {
+            path: entry.path.clone(),
+            dir_type: entry.dir_type.tag(),
+            common_dir,
+            depth: entry.depth,
+        }
     }
+}
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let mut worktrees = Vec::new();
-
-    for line in stdout.lines() {
-        if line.contains("(bare)") {
-            continue;
-        }
-        // Split line on whitespace and take the first part (the path)
-        if let Some((path, _)) = line.split_once(' ') {
-            worktrees.push(PathBuf::from(path));
+fn print_plain(entry: &ProjectDir) {
+    match &entry.dir_type {
+        DirType::WorkTree { common_dir } => {
+            println!("(wt) {} -> {}", entry.path.display(), common_dir.display())
         }
+        other => println!("({}) {}", other.tag(), entry.path.display()),
     }
+}
 
-    Some(worktrees)
+/// Classifies `path` by asking each registered VCS detector in turn, and
+/// returns the detector that claimed it (if any) alongside the verdict.
+fn classify<'a>(
+    path: &Path,
+    detectors: &'a [Box<dyn VersionControl>],
+) -> (DirType, Option<&'a dyn VersionControl>) {
+    for detector in detectors {
+        if let Some(dir_type) = detector.detect(path) {
+            return (dir_type, Some(detector.as_ref()));
+        }
+    }
+    (DirType::Dir, None)
 }
 
 fn process_entries(
     full_path: &Path,
     depth: usize,
-    recursivity_level: Option<usize>,
+    respect_ignore: bool,
+    detectors: &[Box<dyn VersionControl>],
+    cache: &GitCache,
 ) -> Vec<ProjectDir> {
-    let mut results = Vec::new();
-    let level = recursivity_level.unwrap_or(0);
-
-    WalkDir::new(full_path)
-        .min_depth(1)
-        .max_depth(depth)
-        .into_iter()
-        .filter_entry(|e| e.file_type().is_dir())
-        .for_each(|entry| {
-            if let Ok(entry) = entry {
-                let path = entry.path();
-
-                let dir_type = if is_bare_repository(path) {
-                    DirType::BareGit
-                } else if is_git_repository(path) {
-                    DirType::Git
-                } else {
-                    DirType::Dir
-                };
-
-                results.push(ProjectDir {
+    let results = Mutex::new(Vec::new());
+
+    // Beyond the requested depth, keep looking two levels further for a
+    // repository nested inside plain (non-repo) directories -- the same
+    // "look a little deeper" chasing the old code did by recursing into each
+    // plain `Dir`, but folded into this single walk instead of spinning up a
+    // fresh thread pool per directory.
+    let max_depth = depth.saturating_add(2);
+
+    let walker = WalkBuilder::new(full_path)
+        .max_depth(Some(max_depth))
+        .standard_filters(respect_ignore)
+        .hidden(false)
+        .build_parallel();
+
+    walker.run(|| {
+        Box::new(|entry| {
+            let Ok(entry) = entry else {
+                return WalkState::Continue;
+            };
+
+            // min_depth(1): skip full_path itself, same as the old WalkDir call.
+            if entry.depth() == 0 {
+                return WalkState::Continue;
+            }
+            if !entry.file_type().is_some_and(|ft| ft.is_dir()) {
+                return WalkState::Continue;
+            }
+
+            let path = entry.path();
+
+            // Already known to be a repo/worktree root -- skip re-running
+            // detection (config reads, worktrees/ enumeration, ...); another
+            // search root (or an earlier hit in this same walk) already
+            // classified and reported this exact path. Its internals were
+            // already skipped the first time it was seen, so skip them again.
+            if cache.is_known(path) {
+                return WalkState::Skip;
+            }
+
+            let (dir_type, detector) = classify(path, detectors);
+
+            if dir_type == DirType::Dir {
+                // Past the requested depth, plain directories are only
+                // useful as a path to a nested repository, not as rows of
+                // their own.
+                if entry.depth() <= depth {
+                    results.lock().unwrap().push(ProjectDir {
+                        dir_type,
+                        path: path.to_path_buf(),
+                        depth: entry.depth(),
+                    });
+                }
+                // Still a plain directory -- keep descending, since a repo
+                // may be nested further inside it.
+                return WalkState::Continue;
+            }
+
+            let mut found = Vec::new();
+            if cache.record(path) {
+                found.push(ProjectDir {
                     dir_type: dir_type.clone(),
                     path: path.to_path_buf(),
+                    depth: entry.depth(),
                 });
+            }
 
-                if let DirType::Git = dir_type {
-                    // Recursively process the directory for additional Git repositories
-                    // let mut nested_results = process_entries(path, 2)
-                    //     .iter()
-                    //     .filter(|&x| x.dir_type != DirType::Dir)
-                    //     .cloned()
-                    //     .collect();
-                    // results.append(&mut nested_results);
-                }
-                if let DirType::Dir = dir_type {
-                    if level <= 1 {
-                        // Recursively process the directory for additional Git repositories
-                        let mut nested_results = process_entries(path, 1, Some(level + 1))
-                            .iter()
-                            .filter(|&x| x.dir_type != DirType::Dir)
-                            .cloned()
-                            .collect();
-                        results.append(&mut nested_results);
-                    }
-                }
-                if let DirType::BareGit = dir_type {
-                    if let Some(worktrees) = list_worktrees(path) {
-                        for worktree in worktrees {
-                            results.push(ProjectDir {
-                                dir_type: DirType::WorkTree, // Mark worktrees as Git repos
+            // Only bare/common repos carry worktree admin data (a
+            // `worktrees/` dir); asking a plain checkout would at best waste
+            // a `read_dir`, and at worst misread an unrelated top-level
+            // folder that happens to be named `worktrees`.
+            if let DirType::BareGit = dir_type {
+                if let Some(detector) = detector {
+                    for worktree in detector.worktrees(path) {
+                        if cache.record(&worktree) {
+                            found.push(ProjectDir {
+                                dir_type: DirType::WorkTree {
+                                    common_dir: path.to_path_buf(),
+                                },
                                 path: worktree,
+                                depth: entry.depth(),
                             });
                         }
                     }
                 }
             }
-        });
 
+            results.lock().unwrap().extend(found);
+
+            // The entry is an already-classified repo (or worktree); its
+            // internals (`.git/objects`, `refs/`, a bare repo's `hooks`,
+            // `info`, ... ) are plumbing, not directories of interest, and
+            // descending into them would also defeat the point of skipping
+            // detection once a path is known.
+            WalkState::Skip
+        })
+    });
+
+    let mut results = results.into_inner().unwrap();
+    // Worker threads finish in nondeterministic order; sort so output stays
+    // stable across runs regardless of scheduling.
+    results.sort_by(|a, b| a.path.cmp(&b.path));
     results
 }
 
 fn main() {
     let args = Args::parse();
     let home = PathBuf::from(env::var("HOME").expect("Failed to get HOME directory"));
+    let detectors = detectors();
+    let cache = GitCache::new();
 
-    for path in &args.paths {
-        let full_path = expand_path(path, &home);
-        let entries = process_entries(&full_path, args.depth, None);
-
-        for entry in entries {
-            match entry.dir_type {
-                DirType::BareGit => println!("(bare) {}", entry.path.display()),
-                DirType::Git => println!("(git) {}", entry.path.display()),
-                DirType::Dir => println!("(dir) {}", entry.path.display()),
-                DirType::WorkTree => println!("(wt) {}", entry.path.display()),
+    match args.format {
+        // Stream each path's results as soon as they're ready, same as before
+        // JSON support was added.
+        OutputFormat::Plain => {
+            for path in &args.paths {
+                let full_path = expand_path(path, &home);
+                let entries =
+                    process_entries(&full_path, args.depth, !args.no_ignore, &detectors, &cache);
+                entries.iter().for_each(print_plain);
+            }
+        }
+        // A single JSON array needs every path's results gathered first.
+        OutputFormat::Json => {
+            let mut all_entries = Vec::new();
+            for path in &args.paths {
+                let full_path = expand_path(path, &home);
+                all_entries.extend(process_entries(
+                    &full_path,
+                    args.depth,
+                    !args.no_ignore,
+                    &detectors,
+                    &cache,
+                ));
             }
+            let json_entries: Vec<JsonEntry> = all_entries.iter().map(JsonEntry::from).collect();
+            let output =
+                serde_json::to_string_pretty(&json_entries).expect("Failed to serialize entries");
+            println!("{output}");
         }
     }
 }
@@ -0,0 +1,325 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// What kind of version-controlled (or plain) directory a path turned out to be.
+#[derive(Clone, PartialEq, Debug)]
+pub enum DirType {
+    BareGit,
+    /// A linked worktree, together with the bare/common repo it belongs to.
+    WorkTree { common_dir: PathBuf },
+    Git,
+    Mercurial,
+    Jujutsu,
+    Pijul,
+    Dir,
+}
+
+impl DirType {
+    /// Short tag used by the JSON output mode and by the plain-text mode for
+    /// every variant except worktrees, which plain text prints as `(wt)`.
+    pub fn tag(&self) -> &'static str {
+        match self {
+            DirType::BareGit => "bare",
+            DirType::WorkTree { .. } => "worktree",
+            DirType::Git => "git",
+            DirType::Mercurial => "hg",
+            DirType::Jujutsu => "jj",
+            DirType::Pijul => "pijul",
+            DirType::Dir => "dir",
+        }
+    }
+}
+
+/// A detector for one version control system. `process_entries` holds a
+/// registered list of these and asks each in turn whether it recognizes a
+/// directory, instead of hard-coding git everywhere. `Send + Sync` because
+/// the list is shared across the worker threads the parallel walker spawns.
+pub trait VersionControl: Send + Sync {
+    /// Classifies `dir`, or returns `None` if this VCS doesn't own it.
+    fn detect(&self, dir: &Path) -> Option<DirType>;
+
+    /// Linked worktrees belonging to `dir`, if this VCS supports them.
+    fn worktrees(&self, _dir: &Path) -> Vec<PathBuf> {
+        Vec::new()
+    }
+}
+
+/// Returns the registered detectors, tried in order for every directory.
+pub fn detectors() -> Vec<Box<dyn VersionControl>> {
+    vec![
+        Box::new(Git),
+        Box::new(Mercurial),
+        Box::new(Jujutsu),
+        Box::new(Pijul),
+    ]
+}
+
+pub struct Git;
+
+/// A directory is a git dir (as opposed to a plain work tree) if it has the
+/// three markers gix-discover's `is::git` checks for: `HEAD`, `objects/` and
+/// `refs/`.
+fn is_git_dir(dir: &Path) -> bool {
+    dir.join("HEAD").is_file() && dir.join("objects").is_dir() && dir.join("refs").is_dir()
+}
+
+/// Reads `<git_dir>/config` and looks for a `bare` entry, following the same
+/// truthy/falsy tokens git itself accepts (`true`/`yes`/`1`, `false`/`no`/`0`).
+/// Missing config defaults to bare; a config with no `bare` line at all falls
+/// back to the heuristic gix-discover uses: no `index` file and the dir isn't
+/// named `.git`.
+fn is_bare_from_config(dir: &Path) -> bool {
+    let contents = match fs::read_to_string(dir.join("config")) {
+        Ok(contents) => contents,
+        Err(_) => return true,
+    };
+
+    for raw_line in contents.lines() {
+        let line = raw_line.trim();
+        if line == "bare" {
+            return true;
+        }
+        if let Some(value) = line
+            .strip_prefix("bare")
+            .and_then(|rest| rest.trim_start().strip_prefix('='))
+        {
+            return matches!(value.trim(), "true" | "yes" | "1");
+        }
+    }
+
+    !dir.join("index").is_file() && dir.file_name().is_none_or(|name| name != ".git")
+}
+
+fn is_git_repository(dir: &Path) -> bool {
+    dir.join(".git").exists()
+}
+
+fn is_bare_repository(dir: &Path) -> bool {
+    is_git_dir(dir)
+        && dir.file_name().is_none_or(|name| name != ".git")
+        && is_bare_from_config(dir)
+}
+
+/// If `dir`'s `.git` is a file (a linked worktree checkout, as opposed to a
+/// normal repo's `.git` directory), reads its `gitdir:` line and, when that
+/// points into a `worktrees/<name>` directory, resolves and returns the
+/// owning bare/common repo. Mirrors gitoxide's bottom-up linked-worktree
+/// discovery.
+fn worktree_common_dir(dir: &Path) -> Option<PathBuf> {
+    let dot_git = dir.join(".git");
+    if !dot_git.is_file() {
+        return None;
+    }
+
+    let contents = fs::read_to_string(&dot_git).ok()?;
+    let gitdir_line = contents
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("gitdir:"))?;
+    let gitdir = PathBuf::from(gitdir_line.trim());
+    let resolved = if gitdir.is_absolute() {
+        gitdir
+    } else {
+        dir.join(gitdir)
+    };
+
+    let worktrees_dir = resolved.parent()?;
+    if worktrees_dir.file_name()? != "worktrees" {
+        return None;
+    }
+    Some(worktrees_dir.parent()?.to_path_buf())
+}
+
+/// Enumerates the linked worktrees of a bare/common git dir by reading
+/// `worktrees/<name>/gitdir`, which each point at the worktree's `.git` file.
+/// The worktree path is simply that file's parent directory.
+fn list_worktrees(bare_repo_path: &Path) -> Option<Vec<PathBuf>> {
+    let worktrees_dir = bare_repo_path.join("worktrees");
+    if !worktrees_dir.is_dir() {
+        return Some(Vec::new());
+    }
+
+    let mut worktrees = Vec::new();
+    for entry in fs::read_dir(&worktrees_dir).ok()? {
+        let entry = entry.ok()?;
+        if !entry.path().is_dir() {
+            continue;
+        }
+
+        if let Ok(gitdir) = fs::read_to_string(entry.path().join("gitdir")) {
+            if let Some(worktree_path) = Path::new(gitdir.trim()).parent() {
+                worktrees.push(worktree_path.to_path_buf());
+            }
+        }
+    }
+
+    Some(worktrees)
+}
+
+impl VersionControl for Git {
+    fn detect(&self, dir: &Path) -> Option<DirType> {
+        if is_bare_repository(dir) {
+            Some(DirType::BareGit)
+        } else if let Some(common_dir) = worktree_common_dir(dir) {
+            Some(DirType::WorkTree { common_dir })
+        } else if is_git_repository(dir) {
+            Some(DirType::Git)
+        } else {
+            None
+        }
+    }
+
+    fn worktrees(&self, dir: &Path) -> Vec<PathBuf> {
+        list_worktrees(dir).unwrap_or_default()
+    }
+}
+
+pub struct Mercurial;
+
+impl VersionControl for Mercurial {
+    fn detect(&self, dir: &Path) -> Option<DirType> {
+        dir.join(".hg").is_dir().then_some(DirType::Mercurial)
+    }
+}
+
+pub struct Jujutsu;
+
+impl VersionControl for Jujutsu {
+    fn detect(&self, dir: &Path) -> Option<DirType> {
+        dir.join(".jj").is_dir().then_some(DirType::Jujutsu)
+    }
+}
+
+pub struct Pijul;
+
+impl VersionControl for Pijul {
+    fn detect(&self, dir: &Path) -> Option<DirType> {
+        dir.join(".pijul").is_dir().then_some(DirType::Pijul)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh, empty temp dir scoped to one test by name + pid, so parallel
+    /// test runs never collide.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "directory_finder-vcs-test-{name}-{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn bare_true_with_spaces_is_bare() {
+        let dir = temp_dir("bare-true-spaces");
+        fs::write(dir.join("config"), "[core]\n\tbare = true\n").unwrap();
+        assert!(is_bare_from_config(&dir));
+    }
+
+    #[test]
+    fn bare_true_no_spaces_is_bare() {
+        let dir = temp_dir("bare-true-no-spaces");
+        fs::write(dir.join("config"), "[core]\nbare=true\n").unwrap();
+        assert!(is_bare_from_config(&dir));
+    }
+
+    #[test]
+    fn bare_yes_and_one_are_bare() {
+        let dir = temp_dir("bare-yes");
+        fs::write(dir.join("config"), "bare = yes\n").unwrap();
+        assert!(is_bare_from_config(&dir));
+
+        let dir = temp_dir("bare-one");
+        fs::write(dir.join("config"), "bare = 1\n").unwrap();
+        assert!(is_bare_from_config(&dir));
+    }
+
+    #[test]
+    fn bare_false_no_and_zero_are_not_bare() {
+        for value in ["false", "no", "0"] {
+            let dir = temp_dir(&format!("bare-not-{value}"));
+            fs::write(dir.join("config"), format!("bare = {value}\n")).unwrap();
+            assert!(!is_bare_from_config(&dir), "bare = {value} should not be bare");
+        }
+    }
+
+    #[test]
+    fn bare_alone_on_its_own_line_is_bare() {
+        let dir = temp_dir("bare-alone");
+        fs::write(dir.join("config"), "bare\n").unwrap();
+        assert!(is_bare_from_config(&dir));
+    }
+
+    #[test]
+    fn missing_config_file_defaults_to_bare() {
+        let dir = temp_dir("missing-config");
+        assert!(is_bare_from_config(&dir));
+    }
+
+    #[test]
+    fn no_bare_key_falls_back_to_index_heuristic() {
+        let dir = temp_dir("no-bare-key");
+        fs::write(dir.join("config"), "[core]\n\trepositoryformatversion = 0\n").unwrap();
+
+        // No `index` file and the dir isn't named `.git` -> heuristic says bare.
+        assert!(is_bare_from_config(&dir));
+
+        fs::write(dir.join("index"), "").unwrap();
+        assert!(!is_bare_from_config(&dir));
+    }
+
+    #[test]
+    fn worktree_common_dir_resolves_absolute_gitdir() {
+        let repo = temp_dir("worktree-absolute");
+        let worktree = repo.join("checkout");
+        fs::create_dir_all(&worktree).unwrap();
+        let admin_dir = repo.join("worktrees").join("checkout");
+        fs::create_dir_all(&admin_dir).unwrap();
+        fs::write(
+            worktree.join(".git"),
+            format!("gitdir: {}\n", admin_dir.display()),
+        )
+        .unwrap();
+
+        assert_eq!(worktree_common_dir(&worktree), Some(repo));
+    }
+
+    #[test]
+    fn worktree_common_dir_none_without_worktrees_parent() {
+        let dir = temp_dir("submodule-gitfile");
+        fs::write(dir.join(".git"), "gitdir: ../.git/modules/sub\n").unwrap();
+        assert_eq!(worktree_common_dir(&dir), None);
+    }
+
+    #[test]
+    fn worktree_common_dir_none_without_git_file() {
+        let dir = temp_dir("no-dot-git");
+        assert_eq!(worktree_common_dir(&dir), None);
+    }
+
+    #[test]
+    fn list_worktrees_reads_gitdir_files() {
+        let repo = temp_dir("list-worktrees");
+        let worktree = repo.join("checkout");
+        fs::create_dir_all(&worktree).unwrap();
+        let admin_dir = repo.join("worktrees").join("checkout");
+        fs::create_dir_all(&admin_dir).unwrap();
+        fs::write(
+            admin_dir.join("gitdir"),
+            format!("{}\n", worktree.join(".git").display()),
+        )
+        .unwrap();
+
+        assert_eq!(list_worktrees(&repo).unwrap(), vec![worktree]);
+    }
+
+    #[test]
+    fn list_worktrees_empty_without_worktrees_dir() {
+        let repo = temp_dir("no-worktrees-dir");
+        assert_eq!(list_worktrees(&repo), Some(Vec::new()));
+    }
+}